@@ -0,0 +1,14 @@
+-- Migration 012: Server-moved redirects
+--
+-- Maps a server's old hostname to its new one per network, so bookmarked
+-- detail pages (/{network}/{old_host}) 301 to the new host after an operator
+-- migrates. Entries are managed through POST /api/v1/redirects; the newest
+-- row per (module, old_hostname) wins, and an empty new_hostname removes it.
+
+CREATE TABLE IF NOT EXISTS hosh.redirects (
+    module String,
+    old_hostname String,
+    new_hostname String,
+    created_at DateTime64(3, 'UTC') DEFAULT now64(3)
+) ENGINE = ReplacingMergeTree(created_at)
+ORDER BY (module, old_hostname);
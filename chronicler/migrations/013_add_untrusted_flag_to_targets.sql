@@ -0,0 +1,11 @@
+-- Migration 013: Untrusted flag on targets
+--
+-- Marks servers caught misreporting their height (e.g. persistently ahead of
+-- consensus). They stay listed, flagged as untrusted, but are left out of the
+-- percentile and quorum height calculations. Set it by hand, e.g.:
+--
+--   ALTER TABLE hosh.targets UPDATE untrusted = true
+--   WHERE module = 'zec' AND hostname = 'example.com';
+
+ALTER TABLE hosh.targets
+ADD COLUMN IF NOT EXISTS untrusted Boolean DEFAULT false;
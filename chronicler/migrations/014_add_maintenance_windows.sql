@@ -0,0 +1,16 @@
+-- Migration 014: Scheduled maintenance windows
+--
+-- Operators declare planned downtime per server so it doesn't count against
+-- uptime. Checks in any hour a window touches are left out of the uptime
+-- percentages (uptime is aggregated per hour). Windows are managed through
+-- POST /api/v1/maintenance; the newest row per (module, hostname, start_at)
+-- wins, and an end_at equal to start_at cancels the window.
+
+CREATE TABLE IF NOT EXISTS hosh.maintenance_windows (
+    module String,
+    hostname String,
+    start_at DateTime64(3, 'UTC'),
+    end_at DateTime64(3, 'UTC'),
+    created_at DateTime64(3, 'UTC') DEFAULT now64(3)
+) ENGINE = ReplacingMergeTree(created_at)
+ORDER BY (module, hostname, start_at);
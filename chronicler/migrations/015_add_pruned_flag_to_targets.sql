@@ -0,0 +1,12 @@
+-- Migration 015: Pruned flag on targets
+--
+-- Discovery's AUTO_PRUNE_DEAD sets this on targets with no successful check
+-- in AUTO_PRUNE_DEAD_DAYS instead of deleting them. Pruned targets are only
+-- handed out to checkers about once a day; discovery clears the flag as soon
+-- as one of those checks succeeds. It can also be cleared by hand, e.g.:
+--
+--   ALTER TABLE hosh.targets UPDATE pruned = false
+--   WHERE module = 'btc' AND hostname = 'example.com';
+
+ALTER TABLE hosh.targets
+ADD COLUMN IF NOT EXISTS pruned Boolean DEFAULT false;
@@ -3,6 +3,7 @@
 //! This crate provides functionality to check the health of Bitcoin Electrum servers.
 //! It runs in worker mode, polling the web API for jobs and checking servers.
 
+use hosh_core::config::WorkerConfig;
 use tracing::{error, info};
 
 pub mod routes;
@@ -23,12 +24,21 @@ pub async fn run() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 /// multi-region monitoring.
 pub async fn run_with_location(
     location: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    run_with_config(WorkerConfig::from_env(), location).await
+}
+
+/// Run the BTC checker with configuration already loaded by
+/// [`hosh_core::config::AppConfig::load`].
+pub async fn run_with_config(
+    config: WorkerConfig,
+    location: &str,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     info!(
         "Starting BTC checker in worker mode (location: {})...",
         location
     );
-    match worker::Worker::new_with_location(location).await {
+    match worker::Worker::with_config(config, location).await {
         Ok(worker) => {
             if let Err(e) = worker.run().await {
                 error!("Worker error: {}", e);
@@ -1,5 +1,9 @@
 use crate::utils::ElectrumStream;
-use crate::utils::{error_response, send_electrum_request, try_connect};
+use crate::utils::{
+    canonical_host, cert_expires_at, cert_fingerprint, error_response, is_permanent_error,
+    send_electrum_request, server_implementation, try_connect, Connection, ErrorKind,
+    PROTOCOL_MISMATCH,
+};
 use axum::{extract::Query, response::Json};
 use bitcoin::blockdata::block::Header as BlockHeader;
 use bitcoin::consensus::encode::deserialize;
@@ -7,12 +11,55 @@ use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use serde_json::json;
 use std::time::{Duration, UNIX_EPOCH};
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 #[derive(Deserialize)]
 pub struct QueryParams {
     pub url: String,
     pub port: Option<u16>,
+    /// Also time a block header download (`block_fetch_ms`).
+    #[serde(default)]
+    pub deep: bool,
+}
+
+/// Headers requested by a deep check: 100 × 80 bytes ending at the tip.
+const DEEP_CHECK_HEADER_COUNT: u64 = 100;
+
+/// Time fetching the last `DEEP_CHECK_HEADER_COUNT` headers up to `height`,
+/// in milliseconds. `None` if the server fails or returns no headers.
+async fn measure_block_fetch(stream: &mut ElectrumStream, height: u64) -> Option<f64> {
+    let count = DEEP_CHECK_HEADER_COUNT.min(height + 1);
+    let start_height = height + 1 - count;
+    let start_time = std::time::Instant::now();
+
+    let response = match tokio::time::timeout(
+        std::time::Duration::from_secs(10),
+        send_electrum_request(
+            stream,
+            "blockchain.block.headers",
+            vec![json!(start_height), json!(count)],
+        ),
+    )
+    .await
+    {
+        Ok(Ok(response)) => response,
+        Ok(Err(e)) => {
+            debug!("blockchain.block.headers request failed: {}", e);
+            return None;
+        }
+        Err(_) => {
+            debug!("blockchain.block.headers request timed out");
+            return None;
+        }
+    };
+    let elapsed = start_time.elapsed().as_millis() as f64;
+
+    let returned = response
+        .get("result")
+        .and_then(|r| r.get("count"))
+        .and_then(|c| c.as_u64())
+        .unwrap_or(0);
+    (returned > 0).then_some(elapsed)
 }
 
 fn parse_block_header(header_hex: &str) -> Result<serde_json::Value, String> {
@@ -36,6 +83,7 @@ fn parse_block_header(header_hex: &str) -> Result<serde_json::Value, String> {
         .collect::<String>();
 
     Ok(serde_json::json!({
+        "block_hash": header.block_hash().to_string(),
         "version": header.version,
         "prev_block": prev_block,
         "merkle_root": merkle_root,
@@ -54,35 +102,33 @@ pub async fn electrum_query(
 
     info!("📥 Starting query for {}:{}", host, port);
 
-    // Add timeout for the connection
-    let (self_signed, mut stream) =
-        tokio::time::timeout(std::time::Duration::from_secs(10), try_connect(host, port))
-            .await
-            .map_err(|_| {
+    let Connection {
+        self_signed,
+        mut stream,
+    } = tokio::time::timeout(std::time::Duration::from_secs(10), try_connect(host, port))
+        .await
+        .map_err(|_| {
+            error_response(
+                &format!("Connection timeout for {}:{}", host, port),
+                "timeout_error",
+            )
+        })?
+        .map_err(|e| {
+            error!("Connection error for {}:{}: {}", host, port, e);
+            let kind = ErrorKind::from_message(&e);
+            if is_permanent_error(&kind) {
+                warn!(
+                    "Permanent error for {}:{}, recording failure: {}",
+                    host, port, e
+                );
+                error_response(&e, kind.error_type())
+            } else {
                 error_response(
-                    &format!("Connection timeout for {}:{}", host, port),
-                    "timeout_error",
+                    &format!("Failed to connect to {}:{} - {}", host, port, e),
+                    kind.error_type(),
                 )
-            })?
-            .map_err(|e| {
-                error!("Connection error for {}:{}: {}", host, port, e);
-                if e.contains("Failed to connect to .onion via Tor") {
-                    error_response(
-                        &format!("Failed to connect to {}:{} - {}", host, port, e),
-                        "tor_error",
-                    )
-                } else if e.contains("connection refused") || e.contains("Host unreachable") {
-                    error_response(
-                        &format!("Failed to connect to {}:{} - {}", host, port, e),
-                        "host_unreachable",
-                    )
-                } else {
-                    error_response(
-                        &format!("Failed to connect to {}:{} - {}", host, port, e),
-                        "connection_error",
-                    )
-                }
-            })?;
+            }
+        })?;
 
     let version = match tokio::time::timeout(
         std::time::Duration::from_secs(5),
@@ -104,6 +150,13 @@ pub async fn electrum_query(
                 .unwrap_or("unknown")
                 .to_string()
         }
+        Ok(Err(e)) if e.starts_with(PROTOCOL_MISMATCH) => {
+            error!("Version request failed for {}:{}: {}", host, port, e);
+            return Err(error_response(
+                &format!("{} ({}:{})", e, host, port),
+                "protocol_mismatch",
+            ));
+        }
         Ok(Err(e)) => {
             error!("Version request failed: {}", e);
             "unknown".to_string()
@@ -114,10 +167,41 @@ pub async fn electrum_query(
         }
     };
 
+    // Not every server implements server.features; treat any failure as "none"
+    let features = match tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        send_electrum_request(&mut stream, "server.features", vec![]),
+    )
+    .await
+    {
+        Ok(Ok(response)) => response.get("result").filter(|r| r.is_object()).cloned(),
+        Ok(Err(e)) => {
+            debug!("server.features request failed: {}", e);
+            None
+        }
+        Err(_) => {
+            debug!("server.features request timed out");
+            None
+        }
+    };
+
+    let canonical_host = canonical_host(features.as_ref(), host);
+
+    // features.server_version carries the same string when server.version failed
+    let implementation = server_implementation(&version).or_else(|| {
+        features
+            .as_ref()
+            .and_then(|f| f.get("server_version"))
+            .and_then(|v| v.as_str())
+            .and_then(server_implementation)
+    });
+
     let tls_version = match &stream {
         ElectrumStream::Ssl(ssl_stream) => ssl_stream.ssl().version_str().to_string(),
         ElectrumStream::Plain(_) => "None (plaintext)".to_string(),
     };
+    let cert_expires_at = cert_expires_at(&stream);
+    let cert_fingerprint = cert_fingerprint(&stream);
 
     debug!(
         "Connected to {}:{} | TLS Version: {} | Self-signed: {:?}",
@@ -164,6 +248,12 @@ pub async fn electrum_query(
                 .and_then(|v| v.as_u64())
                 .unwrap_or(0);
 
+            let block_fetch_ms = if params.deep && height > 0 {
+                measure_block_fetch(&mut stream, height).await
+            } else {
+                None
+            };
+
             if let Some(hex_str) = response
                 .get("result")
                 .and_then(|r| r.get("hex"))
@@ -177,11 +267,18 @@ pub async fn electrum_query(
                             "host": host,
                             "height": height,
                             "ping": ping,
+                            "block_fetch_ms": block_fetch_ms,
                             "tls_version": tls_version,
                             "self_signed": self_signed,
+                            "cert_expires_at": cert_expires_at,
+                            "cert_fingerprint": cert_fingerprint,
+                            "features": features,
+                            "canonical_host": canonical_host,
+                            "implementation": implementation,
                             "connection_type": connection_type,
                             "resolved_ips": resolved_ips,
                             "server_version": version,
+                            "block_hash": parsed_header["block_hash"],
                             "bits": parsed_header["bits"],
                             "version": parsed_header["version"],
                             "nonce": parsed_header["nonce"],
@@ -208,8 +305,14 @@ pub async fn electrum_query(
                 "host": host,
                 "height": height,
                 "ping": ping,
+                "block_fetch_ms": block_fetch_ms,
                 "tls_version": tls_version,
                 "self_signed": self_signed,
+                "cert_expires_at": cert_expires_at,
+                "cert_fingerprint": cert_fingerprint,
+                "features": features,
+                "canonical_host": canonical_host,
+                "implementation": implementation,
                 "connection_type": connection_type,
                 "resolved_ips": resolved_ips,
                 "server_version": version,
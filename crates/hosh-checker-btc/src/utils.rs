@@ -1,3 +1,5 @@
+use openssl::asn1::Asn1Time;
+use openssl::hash::MessageDigest;
 use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
 use openssl::x509::X509StoreContextRef;
 use serde_json::json;
@@ -36,7 +38,177 @@ impl ElectrumStream {
     }
 }
 
-pub async fn try_connect(host: &str, port: u16) -> Result<(Option<bool>, ElectrumStream), String> {
+/// Not-after date of the server's TLS certificate, as RFC3339.
+/// `None` for plaintext connections or when the peer sent no certificate.
+pub fn cert_expires_at(stream: &ElectrumStream) -> Option<String> {
+    let ElectrumStream::Ssl(ssl_stream) = stream else {
+        return None;
+    };
+    let cert = ssl_stream.ssl().peer_certificate()?;
+
+    // Asn1Time has no direct conversion to chrono, so measure the distance
+    // from "now" in OpenSSL's terms and apply it to our own clock.
+    let now = Asn1Time::days_from_now(0).ok()?;
+    let diff = now.diff(cert.not_after()).ok()?;
+    let expires_at = chrono::Utc::now()
+        + chrono::Duration::days(diff.days as i64)
+        + chrono::Duration::seconds(diff.secs as i64);
+
+    Some(expires_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+}
+
+/// SHA-256 of the server's DER-encoded TLS certificate, as lowercase hex.
+/// Servers presenting the same certificate are almost certainly one instance.
+pub fn cert_fingerprint(stream: &ElectrumStream) -> Option<String> {
+    let ElectrumStream::Ssl(ssl_stream) = stream else {
+        return None;
+    };
+    let cert = ssl_stream.ssl().peer_certificate()?;
+    let digest = cert.digest(MessageDigest::sha256()).ok()?;
+    Some(hex::encode(&*digest))
+}
+
+/// Prefix for errors where the server speaks a different protocol than the
+/// port implies (plaintext on an SSL port or vice versa).
+pub const PROTOCOL_MISMATCH: &str = "Protocol mismatch";
+
+/// Prefix for errors where the hostname doesn't resolve (NXDOMAIN).
+pub const DNS_NOT_FOUND: &str = "DNS lookup failed";
+
+/// Prefix for errors where the certificate chain is valid but was issued for
+/// a different hostname.
+pub const CERT_HOSTNAME_MISMATCH: &str = "Certificate hostname mismatch";
+
+/// OpenSSL's `X509_V_ERR_HOSTNAME_MISMATCH` verify result.
+const X509_V_ERR_HOSTNAME_MISMATCH: i32 = 62;
+
+/// Broad class of a failed connection, recognised from the message
+/// `try_connect` returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    DnsNotFound,
+    CertHostnameMismatch,
+    ProtocolMismatch,
+    Tor,
+    HostUnreachable,
+    Connection,
+}
+
+impl ErrorKind {
+    pub fn from_message(e: &str) -> Self {
+        if e.starts_with(DNS_NOT_FOUND) {
+            ErrorKind::DnsNotFound
+        } else if e.starts_with(CERT_HOSTNAME_MISMATCH) {
+            ErrorKind::CertHostnameMismatch
+        } else if e.starts_with(PROTOCOL_MISMATCH) {
+            ErrorKind::ProtocolMismatch
+        } else if e.contains("Failed to connect to .onion via Tor") {
+            ErrorKind::Tor
+        } else if e.contains("connection refused") || e.contains("Host unreachable") {
+            ErrorKind::HostUnreachable
+        } else {
+            ErrorKind::Connection
+        }
+    }
+
+    /// The `error_type` reported for this kind of failure.
+    pub fn error_type(&self) -> &'static str {
+        match self {
+            ErrorKind::DnsNotFound => "dns_error",
+            ErrorKind::CertHostnameMismatch => "cert_hostname_mismatch",
+            ErrorKind::ProtocolMismatch => "protocol_mismatch",
+            ErrorKind::Tor => "tor_error",
+            ErrorKind::HostUnreachable => "host_unreachable",
+            ErrorKind::Connection => "connection_error",
+        }
+    }
+}
+
+/// Errors that won't go away on another attempt, so the failure is recorded
+/// as-is rather than dressed up as a generic connection error.
+pub fn is_permanent_error(kind: &ErrorKind) -> bool {
+    matches!(
+        kind,
+        ErrorKind::DnsNotFound | ErrorKind::CertHostnameMismatch | ErrorKind::ProtocolMismatch
+    )
+}
+
+/// An established connection plus what we learned about its certificate.
+pub struct Connection {
+    /// `None` for plaintext connections.
+    pub self_signed: Option<bool>,
+    pub stream: ElectrumStream,
+}
+
+/// Whether a connect error means the hostname has no DNS records, as opposed
+/// to a transient resolver failure ("Temporary failure in name resolution").
+fn is_nxdomain(e: &std::io::Error) -> bool {
+    let message = e.to_string();
+    message.contains("Name or service not known")
+        || message.contains("nodename nor servname provided")
+        || message.contains("No address associated with hostname")
+}
+
+/// Whether a failed TLS handshake looks like the peer answered in plaintext.
+/// A plaintext Electrum server replies to our ClientHello with a JSON-RPC
+/// error, which OpenSSL rejects as a malformed TLS record.
+fn is_plaintext_reply(e: &openssl::ssl::Error) -> bool {
+    e.ssl_error().is_some_and(|stack| {
+        stack.errors().iter().any(|err| {
+            matches!(
+                err.reason(),
+                Some("wrong version number") | Some("packet length too long")
+            )
+        })
+    })
+}
+
+/// Whether a reply to a plaintext request is a TLS record (handshake or
+/// alert), meaning the server expects SSL on this port.
+fn is_tls_record(buf: &[u8]) -> bool {
+    matches!(buf, [0x15 | 0x16, 0x03, ..])
+}
+
+/// Hostname the server advertises for itself in `server.features.hosts`.
+/// If `host` is one of the advertised names it is canonical; otherwise the
+/// first clearnet name wins (falling back to an onion name).
+pub fn canonical_host(features: Option<&serde_json::Value>, host: &str) -> Option<String> {
+    let hosts = features?.get("hosts")?.as_object()?;
+    let names: Vec<&str> = hosts
+        .keys()
+        .map(|k| k.as_str())
+        .filter(|k| !k.is_empty())
+        .collect();
+
+    let is_target = |name: &str| {
+        name.trim_end_matches('.')
+            .eq_ignore_ascii_case(host.trim_end_matches('.'))
+    };
+    names
+        .iter()
+        .copied()
+        .find(|name| is_target(name))
+        .or_else(|| names.iter().copied().find(|name| !name.ends_with(".onion")))
+        .or_else(|| names.first().copied())
+        .map(|name| name.to_string())
+}
+
+/// Electrum server software, recognised from its `server.version` string
+/// (e.g. "Fulcrum 1.9.1", "ElectrumX 1.16.0", "electrs/0.10.0").
+pub fn server_implementation(version: &str) -> Option<&'static str> {
+    let version = version.to_ascii_lowercase();
+    if version.contains("fulcrum") {
+        Some("Fulcrum")
+    } else if version.contains("electrumx") {
+        Some("ElectrumX")
+    } else if version.contains("electrs") {
+        Some("electrs")
+    } else {
+        None
+    }
+}
+
+pub async fn try_connect(host: &str, port: u16) -> Result<Connection, String> {
     info!("Attempting connection to {}:{}", host, port);
 
     let stream = if host.ends_with(".onion") {
@@ -66,7 +238,11 @@ pub async fn try_connect(host: &str, port: u16) -> Result<(Option<bool>, Electru
         let addr = format!("{}:{}", host, port);
         TcpStream::connect(&addr).await.map_err(|e| {
             error!("Failed to connect to {}:{} - {}", host, port, e);
-            format!("Failed to connect to {}:{} - {}", host, port, e)
+            if is_nxdomain(&e) {
+                format!("{}: {} does not resolve - {}", DNS_NOT_FOUND, host, e)
+            } else {
+                format!("Failed to connect to {}:{} - {}", host, port, e)
+            }
         })?
     };
 
@@ -75,7 +251,10 @@ pub async fn try_connect(host: &str, port: u16) -> Result<(Option<bool>, Electru
     // Plaintext connection (Port 50001)
     if port == 50001 {
         info!("Using plaintext connection (no SSL)");
-        return Ok((None, ElectrumStream::Plain(stream)));
+        return Ok(Connection {
+            self_signed: None,
+            stream: ElectrumStream::Plain(stream),
+        });
     }
 
     debug!("Establishing SSL connection...");
@@ -85,16 +264,23 @@ pub async fn try_connect(host: &str, port: u16) -> Result<(Option<bool>, Electru
         format!("Failed to create OpenSSL connector: {:?}", e)
     })?;
 
-    // Track self-signed certificates
+    // Track self-signed certificates, and hostname mismatches separately:
+    // self-signed certs rarely name the host, but a CA-issued one should
     let self_signed_flag = Arc::new(AtomicBool::new(false));
     let flag_clone = Arc::clone(&self_signed_flag);
+    let hostname_mismatch_flag = Arc::new(AtomicBool::new(false));
+    let mismatch_clone = Arc::clone(&hostname_mismatch_flag);
 
     connector_builder.set_verify_callback(
         SslVerifyMode::PEER,
-        move |valid, _ctx: &mut X509StoreContextRef| {
+        move |valid, ctx: &mut X509StoreContextRef| {
             if !valid {
-                warn!("Self-signed certificate detected");
-                flag_clone.store(true, Ordering::Relaxed);
+                if ctx.error().as_raw() == X509_V_ERR_HOSTNAME_MISMATCH {
+                    mismatch_clone.store(true, Ordering::Relaxed);
+                } else {
+                    warn!("Self-signed certificate detected");
+                    flag_clone.store(true, Ordering::Relaxed);
+                }
                 return true; // Allow self-signed certs
             }
             valid
@@ -125,12 +311,38 @@ pub async fn try_connect(host: &str, port: u16) -> Result<(Option<bool>, Electru
     match pinned_stream.as_mut().do_handshake().await {
         Ok(()) => {
             let self_signed = self_signed_flag.load(Ordering::Relaxed);
+            // A CA-issued certificate for another host won't fix itself on a
+            // retry, so fail instead of accepting it like a self-signed one
+            if hostname_mismatch_flag.load(Ordering::Relaxed) && !self_signed {
+                error!(
+                    "Certificate for {}:{} is valid but issued for another host",
+                    host, port
+                );
+                return Err(format!(
+                    "{}: {}:{} presented a valid certificate issued for a different hostname",
+                    CERT_HOSTNAME_MISMATCH, host, port
+                ));
+            }
             let tls_version = ssl_stream.ssl().version_str();
             info!(
                 "SSL handshake successful with {}:{} (TLS: {}, self_signed: {})",
                 host, port, tls_version, self_signed
             );
-            Ok((Some(self_signed), ElectrumStream::Ssl(ssl_stream)))
+            Ok(Connection {
+                self_signed: Some(self_signed),
+                stream: ElectrumStream::Ssl(ssl_stream),
+            })
+        }
+        Err(e) if is_plaintext_reply(&e) => {
+            error!(
+                "SSL handshake with {}:{} got a plaintext reply - {:?}",
+                host, port, e
+            );
+            Err(format!(
+                "{}: {}:{} answered the TLS handshake in plaintext. \
+                 The port is configured as SSL but the server looks like a TCP (non-SSL) Electrum port.",
+                PROTOCOL_MISMATCH, host, port
+            ))
         }
         Err(e) => {
             error!("SSL handshake failed with {}:{} - {:?}", host, port, e);
@@ -211,6 +423,15 @@ pub async fn send_electrum_request(
             format!("Read error: {}", e)
         })?;
 
+    if matches!(stream, ElectrumStream::Plain(_)) && is_tls_record(&buffer) {
+        error!("Received a TLS record on a plaintext connection");
+        return Err(format!(
+            "{}: the server replied with TLS on a plaintext connection. \
+             The port is configured as TCP but the server looks like an SSL Electrum port.",
+            PROTOCOL_MISMATCH
+        ));
+    }
+
     let response_str = String::from_utf8_lossy(&buffer);
     debug!("Received response: {}", response_str.trim());
 
@@ -232,3 +453,60 @@ pub fn error_response(message: &str, error_type: &str) -> axum::response::Respon
         .body(axum::body::Body::from(error_body.to_string()))
         .unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_kind_from_message() {
+        let cases = [
+            (
+                "DNS lookup failed: gone.example does not resolve - failed to lookup address",
+                ErrorKind::DnsNotFound,
+            ),
+            (
+                "Certificate hostname mismatch: a.example:50002 presented a valid certificate issued for a different hostname",
+                ErrorKind::CertHostnameMismatch,
+            ),
+            (
+                "Protocol mismatch: a.example:50002 answered the TLS handshake in plaintext.",
+                ErrorKind::ProtocolMismatch,
+            ),
+            (
+                "Failed to connect to .onion via Tor: general SOCKS server failure",
+                ErrorKind::Tor,
+            ),
+            (
+                "Failed to connect to a.example:50002 - Connection refused (os error 111): connection refused",
+                ErrorKind::HostUnreachable,
+            ),
+            (
+                "Failed to connect to a.example:50002 - Host unreachable",
+                ErrorKind::HostUnreachable,
+            ),
+            (
+                "SSL handshake failed with a.example:50002 - unexpected EOF",
+                ErrorKind::Connection,
+            ),
+            // The prefixes only count at the start of the message
+            (
+                "SSL handshake failed - Protocol mismatch",
+                ErrorKind::Connection,
+            ),
+        ];
+        for (message, kind) in cases {
+            assert_eq!(ErrorKind::from_message(message), kind, "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_is_permanent_error() {
+        assert!(is_permanent_error(&ErrorKind::DnsNotFound));
+        assert!(is_permanent_error(&ErrorKind::CertHostnameMismatch));
+        assert!(is_permanent_error(&ErrorKind::ProtocolMismatch));
+        assert!(!is_permanent_error(&ErrorKind::Tor));
+        assert!(!is_permanent_error(&ErrorKind::HostUnreachable));
+        assert!(!is_permanent_error(&ErrorKind::Connection));
+    }
+}
@@ -1,8 +1,8 @@
 use crate::routes::electrum::query::{electrum_query, QueryParams};
 use axum::extract::Query;
 use futures_util::stream::StreamExt;
+use hosh_core::config::WorkerConfig;
 use serde::{Deserialize, Serialize};
-use std::env;
 use tracing::{debug, error, info};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -50,7 +50,29 @@ struct ServerData {
     status: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     additional_data: Option<serde_json::Value>,
+    /// Not-after date of the server's TLS certificate (RFC3339), SSL only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cert_expires_at: Option<String>,
+    /// SHA-256 fingerprint of the server's TLS certificate (hex), SSL only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cert_fingerprint: Option<String>,
+    /// Raw `server.features` result, when the server implements it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    features: Option<serde_json::Value>,
+    /// Hostname the server advertises in `features.hosts`, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    canonical_host: Option<String>,
+    /// Server software ("Fulcrum", "ElectrumX", "electrs"), when recognised.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    implementation: Option<String>,
+    /// Hash of the tip header returned by `blockchain.headers.subscribe`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block_hash: Option<String>,
+    /// Time to download recent block headers; deep checks only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block_fetch_ms: Option<f64>,
     checker_location: String,
+    checker_id: String,
 }
 
 #[derive(Clone)]
@@ -60,6 +82,15 @@ pub struct Worker {
     max_concurrent_checks: usize,
     http_client: reqwest::Client,
     location: String,
+    /// Random per-process ID sent with each result, so the web UI can tell
+    /// which checker instance reported it.
+    checker_id: String,
+    /// Ask for recently-failed servers first (`PRIORITIZE_FAILING=true`).
+    prioritize_failing: bool,
+    /// Also time a block header download on each check (`DEEP_CHECK=true`).
+    deep_check: bool,
+    /// `btc`, or `btc-canary` when running as a canary (`CANARY=true`).
+    checker_module: String,
 }
 
 impl Worker {
@@ -70,24 +101,47 @@ impl Worker {
     pub async fn new_with_location(
         location: &str,
     ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        let web_api_url = env::var("WEB_API_URL").unwrap_or_else(|_| "http://web:8080".to_string());
-        let api_key = env::var("API_KEY").expect("API_KEY environment variable must be set");
+        Self::with_config(WorkerConfig::from_env(), location).await
+    }
+
+    pub async fn with_config(
+        config: WorkerConfig,
+        location: &str,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let WorkerConfig {
+            web_api_url,
+            api_key,
+            max_concurrent_checks,
+            prioritize_failing,
+            deep_check,
+            canary,
+            ..
+        } = config;
 
+        let checker_id = uuid::Uuid::new_v4().to_string();
         info!(
-            "🚀 Initializing BTC Worker with web API URL: {} (location: {})",
-            web_api_url, location
+            "🚀 Initializing BTC Worker with web API URL: {} (location: {}, id: {})",
+            web_api_url, location, checker_id
         );
 
-        let max_concurrent_checks = env::var("MAX_CONCURRENT_CHECKS")
-            .unwrap_or_else(|_| "3".to_string())
-            .parse()
-            .unwrap_or(10);
-
         info!(
             "⚙️ Setting max concurrent checks to: {}",
             max_concurrent_checks
         );
 
+        if prioritize_failing {
+            info!("⚙️ Prioritizing recently failed servers");
+        }
+
+        if deep_check {
+            info!("⚙️ Deep checks enabled: timing block header downloads");
+        }
+
+        let checker_module = if canary { "btc-canary" } else { "btc" }.to_string();
+        if canary {
+            info!("⚙️ Running as a canary: reporting as {}", checker_module);
+        }
+
         // Create a pooled HTTP client
         info!("🌐 Creating HTTP client with connection pooling...");
         let http_client = reqwest::Client::builder()
@@ -103,6 +157,10 @@ impl Worker {
             max_concurrent_checks,
             http_client,
             location: location.to_string(),
+            checker_id,
+            prioritize_failing,
+            deep_check,
+            checker_module,
         })
     }
 
@@ -114,6 +172,7 @@ impl Worker {
         let params = QueryParams {
             url: request.host.clone(),
             port: Some(request.port),
+            deep: self.deep_check,
         };
 
         match electrum_query(Query(params)).await {
@@ -146,7 +205,7 @@ impl Worker {
                 );
 
                 Some(ServerData {
-                    checker_module: "btc".to_string(),
+                    checker_module: self.checker_module.clone(),
                     hostname: request.host.clone(),
                     host: request.host.clone(),
                     port: request.port,
@@ -166,19 +225,57 @@ impl Worker {
                     check_id: request.get_check_id(),
                     status: "online".to_string(),
                     additional_data: Some(filtered_data),
+                    cert_expires_at: data
+                        .get("cert_expires_at")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string()),
+                    cert_fingerprint: data
+                        .get("cert_fingerprint")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string()),
+                    features: data.get("features").filter(|f| f.is_object()).cloned(),
+                    canonical_host: data
+                        .get("canonical_host")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string()),
+                    implementation: data
+                        .get("implementation")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string()),
+                    block_hash: data
+                        .get("block_hash")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string()),
+                    block_fetch_ms: data.get("block_fetch_ms").and_then(|v| v.as_f64()),
                     checker_location: self.location.clone(),
+                    checker_id: self.checker_id.clone(),
                 })
             }
             Err(e) => {
-                // Extract error message in a serializable format
-                let error_message = format!("Failed to query server: {:?}", e);
+                // error_response() carries the classified error in its JSON body
+                let body = axum::body::to_bytes(e.into_body(), 64 * 1024)
+                    .await
+                    .ok()
+                    .and_then(|bytes| serde_json::from_slice::<serde_json::Value>(&bytes).ok())
+                    .unwrap_or_default();
+                let error_type = body
+                    .get("error_type")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("connection_error")
+                    .to_string();
+                let error_message = format!(
+                    "Failed to query server: {}",
+                    body.get("error")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("unknown error")
+                );
                 error!(
                     "❌ Failed to query server {}:{} - {}",
                     request.host, request.port, error_message
                 );
 
                 Some(ServerData {
-                    checker_module: "btc".to_string(),
+                    checker_module: self.checker_module.clone(),
                     hostname: request.host.clone(),
                     host: request.host.clone(),
                     port: request.port,
@@ -188,13 +285,21 @@ impl Worker {
                     ping: None,
                     ping_ms: None,
                     error: true,
-                    error_type: Some("connection_error".to_string()),
+                    error_type: Some(error_type),
                     error_message: Some(error_message),
                     user_submitted: request.user_submitted,
                     check_id: request.get_check_id(),
                     status: "offline".to_string(),
                     additional_data: None,
+                    cert_expires_at: None,
+                    cert_fingerprint: None,
+                    features: None,
+                    canonical_host: None,
+                    implementation: None,
+                    block_hash: None,
+                    block_fetch_ms: None,
                     checker_location: self.location.clone(),
+                    checker_id: self.checker_id.clone(),
                 })
             }
         }
@@ -280,10 +385,13 @@ impl Worker {
 
         loop {
             info!("📡 Fetching jobs from web API...");
-            let jobs_url = format!(
-                "{}/api/v1/jobs?api_key={}&checker_module=btc&limit={}",
-                self.web_api_url, self.api_key, self.max_concurrent_checks
+            let mut jobs_url = format!(
+                "{}/api/v1/jobs?api_key={}&checker_module={}&limit={}",
+                self.web_api_url, self.api_key, self.checker_module, self.max_concurrent_checks
             );
+            if self.prioritize_failing {
+                jobs_url.push_str("&prioritize=failing");
+            }
             match self.http_client.get(&jobs_url).send().await {
                 Ok(response) => {
                     if response.status().is_success() {
@@ -5,21 +5,41 @@
 //! (for .onion addresses via Tor).
 
 use chrono::{DateTime, Utc};
+use hosh_core::config::WorkerConfig;
 use serde::{Deserialize, Serialize};
 use std::time::Instant;
 use std::{env, error::Error, time::Duration};
 use tonic::{
-    transport::{ClientTlsConfig, Endpoint, Uri},
+    transport::{Channel, ClientTlsConfig, Endpoint, Uri},
     Request,
 };
 use tracing::{error, info};
 use zcash_client_backend::proto::service::{
-    compact_tx_streamer_client::CompactTxStreamerClient, Empty,
+    compact_tx_streamer_client::CompactTxStreamerClient, BlockId, ChainSpec, Empty,
 };
 
 mod socks_connector;
 use socks_connector::SocksConnector;
 
+mod tls_probe;
+use tls_probe::{probe_certificate, PeerCertificate};
+
+/// A server this many blocks or more behind the chain tip it estimates
+/// (`estimated_height` in `GetLightdInfo`) is reported as "syncing".
+const SYNCING_LAG_BLOCKS: u64 = 10;
+
+/// Result status for a check: "offline" on error, "syncing" when the server
+/// is still catching up to its own estimate of the tip, otherwise "online".
+fn check_status(failed: bool, height: u64, estimated_height: Option<u64>) -> &'static str {
+    if failed {
+        "offline"
+    } else if estimated_height.is_some_and(|tip| tip >= height.saturating_add(SYNCING_LAG_BLOCKS)) {
+        "syncing"
+    } else {
+        "online"
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct CheckRequest {
     host: String,
@@ -72,7 +92,20 @@ struct CheckResult {
     zcashd_subversion: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     donation_address: Option<String>,
+    /// Hash of the block at `height`, in the usual big-endian display order.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block_hash: Option<String>,
+    /// Time to download the compact block at `height`; deep checks only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block_fetch_ms: Option<f64>,
+    /// SHA-256 of the server's TLS certificate (hex); direct connections only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cert_fingerprint: Option<String>,
+    /// Not-after date of the server's TLS certificate, as RFC3339.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cert_expires_at: Option<String>,
     checker_location: String,
+    checker_id: String,
 }
 
 #[derive(Debug)]
@@ -92,6 +125,13 @@ struct ServerInfo {
     zcashd_build: String,
     zcashd_subversion: String,
     donation_address: String,
+    block_hash: Option<String>,
+    block_fetch_ms: Option<f64>,
+    /// From `probe_certificate`; `None` over SOCKS or if the probe failed.
+    certificate: Option<PeerCertificate>,
+    /// Connect plus `GetLightdInfo`, taken before the `GetLatestBlock` and
+    /// deep-check calls so their time (or timeouts) never counts as ping.
+    latency_ms: f64,
 }
 
 // ClickhouseConfig removed - not used in current implementation
@@ -103,11 +143,87 @@ struct Worker {
     api_key: String,
     http_client: reqwest::Client,
     location: String,
+    /// Random per-process ID sent with each result, so the web UI can tell
+    /// which checker instance reported it.
+    checker_id: String,
+    /// Ask for recently-failed servers first (`PRIORITIZE_FAILING=true`).
+    prioritize_failing: bool,
+    /// Also time a block download on each check (`DEEP_CHECK=true`).
+    deep_check: bool,
+    /// `zec`, or `zec-canary` when running as a canary (`CANARY=true`).
+    checker_module: String,
+}
+
+/// Hash of the server's latest block, if it's the block at `height`.
+///
+/// Best-effort: a failed call or a tip that moved since `GetLightdInfo`
+/// yields `None` rather than failing the whole check.
+async fn fetch_tip_hash(
+    client: &mut CompactTxStreamerClient<Channel>,
+    height: u64,
+) -> Option<String> {
+    let mut req = Request::new(ChainSpec {});
+    req.set_timeout(Duration::from_secs(10));
+
+    let block = match client.get_latest_block(req).await {
+        Ok(response) => response.into_inner(),
+        Err(e) => {
+            info!("GetLatestBlock failed, skipping block hash: {}", e);
+            return None;
+        }
+    };
+
+    if block.height != height || block.hash.is_empty() {
+        return None;
+    }
+
+    // lightwalletd sends the hash in internal (little-endian) byte order
+    Some(
+        block
+            .hash
+            .iter()
+            .rev()
+            .map(|b| format!("{:02x}", b))
+            .collect(),
+    )
+}
+
+/// Milliseconds taken to download the compact block at `height`.
+///
+/// Best-effort like `fetch_tip_hash`: a failed call yields `None`.
+async fn measure_block_fetch(
+    client: &mut CompactTxStreamerClient<Channel>,
+    height: u64,
+) -> Option<f64> {
+    let mut req = Request::new(BlockId {
+        height,
+        hash: vec![],
+    });
+    req.set_timeout(Duration::from_secs(10));
+
+    let start_time = Instant::now();
+    match client.get_block(req).await {
+        Ok(_) => {
+            let elapsed = start_time.elapsed().as_secs_f64() * 1000.0;
+            Some((elapsed * 100.0).round() / 100.0)
+        }
+        Err(e) => {
+            info!("GetBlock failed, skipping block fetch timing: {}", e);
+            None
+        }
+    }
 }
 
 // Connect directly (without SOCKS proxy)
-async fn get_info_direct(uri: Uri) -> Result<ServerInfo, Box<dyn Error + Send + Sync>> {
+async fn get_info_direct(uri: Uri, deep: bool) -> Result<ServerInfo, Box<dyn Error + Send + Sync>> {
+    // Its own connection, so it stays out of the ping
+    let certificate = match uri.host() {
+        Some(host) => probe_certificate(host, uri.port_u16().unwrap_or(443)).await,
+        None => None,
+    };
+
     info!("Connecting to lightwalletd server at {}", uri);
+    let start_time = Instant::now();
 
     let endpoint = Endpoint::from(uri.clone())
         .tls_config(ClientTlsConfig::new().with_webpki_roots())?
@@ -134,6 +250,14 @@ async fn get_info_direct(uri: Uri) -> Result<ServerInfo, Box<dyn Error + Send +
         }
     };
 
+    let latency_ms = start_time.elapsed().as_secs_f64() * 1000.0;
+    let block_hash = fetch_tip_hash(&mut client, chain_info.block_height).await;
+    let block_fetch_ms = if deep {
+        measure_block_fetch(&mut client, chain_info.block_height).await
+    } else {
+        None
+    };
+
     info!("Processing server response...");
     let info = ServerInfo {
         block_height: chain_info.block_height,
@@ -151,6 +275,10 @@ async fn get_info_direct(uri: Uri) -> Result<ServerInfo, Box<dyn Error + Send +
         zcashd_build: chain_info.zcashd_build,
         zcashd_subversion: chain_info.zcashd_subversion,
         donation_address: chain_info.donation_address,
+        block_hash,
+        block_fetch_ms,
+        certificate,
+        latency_ms,
     };
 
     info!("Successfully gathered server info");
@@ -161,11 +289,13 @@ async fn get_info_direct(uri: Uri) -> Result<ServerInfo, Box<dyn Error + Send +
 async fn get_info_via_socks(
     uri: Uri,
     proxy_addr: String,
+    deep: bool,
 ) -> Result<ServerInfo, Box<dyn Error + Send + Sync>> {
     info!(
         "Connecting to lightwalletd server at {} via SOCKS proxy {}",
         uri, proxy_addr
     );
+    let start_time = Instant::now();
 
     let connector = SocksConnector::new(proxy_addr);
 
@@ -213,6 +343,14 @@ async fn get_info_via_socks(
         }
     };
 
+    let latency_ms = start_time.elapsed().as_secs_f64() * 1000.0;
+    let block_hash = fetch_tip_hash(&mut client, chain_info.block_height).await;
+    let block_fetch_ms = if deep {
+        measure_block_fetch(&mut client, chain_info.block_height).await
+    } else {
+        None
+    };
+
     info!("Processing server response...");
     let info = ServerInfo {
         block_height: chain_info.block_height,
@@ -230,6 +368,10 @@ async fn get_info_via_socks(
         zcashd_build: chain_info.zcashd_build,
         zcashd_subversion: chain_info.zcashd_subversion,
         donation_address: chain_info.donation_address,
+        block_hash,
+        block_fetch_ms,
+        certificate: None,
+        latency_ms,
     };
 
     info!("Successfully gathered server info via SOCKS");
@@ -243,18 +385,45 @@ impl Worker {
     }
 
     pub async fn new_with_location(location: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        Self::with_config(WorkerConfig::from_env(), location).await
+    }
+
+    pub async fn with_config(
+        config: WorkerConfig,
+        location: &str,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
         rustls::crypto::ring::default_provider()
             .install_default()
             .expect("Failed to install rustls crypto provider");
 
-        let web_api_url = env::var("WEB_API_URL").unwrap_or_else(|_| "http://web:8080".to_string());
-        let api_key = env::var("API_KEY").expect("API_KEY environment variable must be set");
+        let WorkerConfig {
+            web_api_url,
+            api_key,
+            prioritize_failing,
+            deep_check,
+            canary,
+            ..
+        } = config;
 
+        let checker_id = uuid::Uuid::new_v4().to_string();
         info!(
-            "🚀 Initializing ZEC Worker with web API URL: {} (location: {})",
-            web_api_url, location
+            "🚀 Initializing ZEC Worker with web API URL: {} (location: {}, id: {})",
+            web_api_url, location, checker_id
         );
 
+        if prioritize_failing {
+            info!("⚙️ Prioritizing recently failed servers");
+        }
+
+        if deep_check {
+            info!("⚙️ Deep checks enabled: timing block downloads");
+        }
+
+        let checker_module = if canary { "zec-canary" } else { "zec" }.to_string();
+        if canary {
+            info!("⚙️ Running as a canary: reporting as {}", checker_module);
+        }
+
         let http_client = reqwest::Client::builder()
             .pool_idle_timeout(std::time::Duration::from_secs(300))
             .pool_max_idle_per_host(32)
@@ -266,6 +435,10 @@ impl Worker {
             api_key,
             http_client,
             location: location.to_string(),
+            checker_id,
+            prioritize_failing,
+            deep_check,
+            checker_module,
         })
     }
 
@@ -319,7 +492,7 @@ impl Worker {
             // .onion addresses require SOCKS proxy
             if let Some(proxy) = socks_proxy {
                 info!("Using SOCKS proxy for .onion address: {}", proxy);
-                match get_info_via_socks(uri, proxy).await {
+                match get_info_via_socks(uri, proxy, self.deep_check).await {
                     Ok(info) => (info.block_height, None, Some(info)),
                     Err(e) => {
                         error!("SOCKS connection failed: {}", e);
@@ -337,7 +510,7 @@ impl Worker {
         } else {
             // Use direct connection for regular addresses
             info!("Using direct connection");
-            match get_info_direct(uri).await {
+            match get_info_direct(uri, self.deep_check).await {
                 Ok(info) => (info.block_height, None, Some(info)),
                 Err(e) => {
                     let simplified_error = if e.to_string().contains("tls handshake eof") {
@@ -370,9 +543,9 @@ impl Worker {
 
         // Only calculate meaningful ping for successful connections
         // For failed .onion connections, don't record the failure time as "ping"
-        let (ping, ping_ms) = if error.is_none() {
-            let latency = start_time.elapsed().as_secs_f64() * 1000.0;
-            let ping_value = (latency * 100.0).round() / 100.0;
+        let (ping, ping_ms) = if let Some(info) = &server_info {
+            // Taken before the tip hash and deep-check calls
+            let ping_value = (info.latency_ms * 100.0).round() / 100.0;
             (ping_value, ping_value)
         } else if is_onion {
             // Don't record ping for failed .onion connections
@@ -405,16 +578,17 @@ impl Worker {
         }
 
         let result = CheckResult {
-            checker_module: "zec".to_string(),
+            checker_module: self.checker_module.clone(),
             hostname: check_request.host.clone(),
             host: check_request.host.clone(),
             port: check_request.port,
             height,
-            status: if error.is_none() {
-                "online".to_string()
-            } else {
-                "offline".to_string()
-            },
+            status: check_status(
+                error.is_some(),
+                height,
+                server_info.as_ref().map(|info| info.estimated_height),
+            )
+            .to_string(),
             error,
             last_updated: Utc::now(),
             ping,
@@ -443,7 +617,20 @@ impl Worker {
             donation_address: server_info
                 .as_ref()
                 .map(|info| info.donation_address.clone()),
+            block_hash: server_info
+                .as_ref()
+                .and_then(|info| info.block_hash.clone()),
+            block_fetch_ms: server_info.as_ref().and_then(|info| info.block_fetch_ms),
+            cert_fingerprint: server_info
+                .as_ref()
+                .and_then(|info| info.certificate.as_ref())
+                .map(|certificate| certificate.fingerprint.clone()),
+            cert_expires_at: server_info
+                .as_ref()
+                .and_then(|info| info.certificate.as_ref())
+                .and_then(|certificate| certificate.expires_at.clone()),
             checker_location: self.location.clone(),
+            checker_id: self.checker_id.clone(),
         };
 
         if let Err(e) = self.submit_to_api(&result).await {
@@ -467,18 +654,30 @@ pub async fn run() -> Result<(), Box<dyn Error + Send + Sync>> {
 /// The location identifier is included in all check results to enable
 /// multi-region monitoring.
 pub async fn run_with_location(location: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    run_with_config(WorkerConfig::from_env(), location).await
+}
+
+/// Run the ZEC checker worker with configuration already loaded by
+/// [`hosh_core::config::AppConfig::load`].
+pub async fn run_with_config(
+    config: WorkerConfig,
+    location: &str,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
     info!(
         "Starting ZEC checker in normal mode (location: {})",
         location
     );
-    let worker = Worker::new_with_location(location).await?;
+    let worker = Worker::with_config(config, location).await?;
 
     loop {
         info!("📡 Fetching jobs from web API...");
-        let jobs_url = format!(
-            "{}/api/v1/jobs?api_key={}&checker_module=zec&limit=10",
-            worker.web_api_url, worker.api_key
+        let mut jobs_url = format!(
+            "{}/api/v1/jobs?api_key={}&checker_module={}&limit=10",
+            worker.web_api_url, worker.api_key, worker.checker_module
         );
+        if worker.prioritize_failing {
+            jobs_url.push_str("&prioritize=failing");
+        }
         match worker.http_client.get(&jobs_url).send().await {
             Ok(response) => {
                 if response.status().is_success() {
@@ -583,30 +782,28 @@ pub async fn test_connection(target: &str) -> Result<(), Box<dyn Error + Send +
         "Starting connection attempt to {} (timeout: {} seconds)...",
         uri, wait_time
     );
-    let start_time = Instant::now();
 
     // Only use SOCKS for .onion addresses
     let connection_result = if is_onion {
         if let Some(proxy) = socks_proxy {
             tokio::time::timeout(
                 Duration::from_secs(wait_time),
-                get_info_via_socks(uri, proxy),
+                get_info_via_socks(uri, proxy, false),
             )
             .await
         } else {
             return Err("Cannot connect to .onion address without SOCKS proxy".into());
         }
     } else {
-        tokio::time::timeout(Duration::from_secs(wait_time), get_info_direct(uri)).await
+        tokio::time::timeout(Duration::from_secs(wait_time), get_info_direct(uri, false)).await
     };
 
     match connection_result {
         Ok(result) => match result {
             Ok(info) => {
-                let latency = start_time.elapsed().as_secs_f64() * 1000.0;
                 info!(
                     "Successfully connected! Block height: {}, Latency: {:.2}ms",
-                    info.block_height, latency
+                    info.block_height, info.latency_ms
                 );
                 info!(
                     "Server details: vendor={}, version={}, chain={}",
@@ -0,0 +1,269 @@
+//! Reads a server's TLS certificate with a separate handshake.
+//!
+//! tonic completes the handshake inside the gRPC channel and never exposes
+//! the peer certificate, so before connecting the channel we open a plain
+//! rustls connection, take the certificate and close it again.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, SignatureScheme};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tokio_rustls::TlsConnector;
+use tracing::info;
+
+/// What the probe learned about the server's leaf certificate.
+#[derive(Debug)]
+pub struct PeerCertificate {
+    /// SHA-256 of the DER-encoded certificate, as lowercase hex.
+    pub fingerprint: String,
+    /// Not-after date as RFC3339, like the BTC checker reports it. `None` if
+    /// the certificate couldn't be parsed.
+    pub expires_at: Option<String>,
+}
+
+impl PeerCertificate {
+    fn from_der(der: &[u8]) -> Self {
+        Self {
+            fingerprint: hex::encode(Sha256::digest(der)),
+            expires_at: cert_not_after(der)
+                .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+        }
+    }
+}
+
+const DER_SEQUENCE: u8 = 0x30;
+const DER_UTC_TIME: u8 = 0x17;
+const DER_GENERALIZED_TIME: u8 = 0x18;
+/// The explicit `[0]` tag around an X.509 `version`.
+const DER_VERSION: u8 = 0xa0;
+
+/// The first DER element of `input`: its tag, its contents and what follows.
+fn der_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (usize::from(first), rest)
+    } else {
+        // Long form: the low bits count the length bytes that follow
+        let count = usize::from(first & 0x7f);
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let len = rest[..count]
+            .iter()
+            .fold(0usize, |len, &byte| (len << 8) | usize::from(byte));
+        (len, &rest[count..])
+    };
+    (rest.len() >= len).then(|| (tag, &rest[..len], &rest[len..]))
+}
+
+/// `notAfter` of a DER-encoded X.509 certificate. Only walks as far into the
+/// `TBSCertificate` as the validity period, so there's no need for a full
+/// X.509 parser.
+fn cert_not_after(der: &[u8]) -> Option<DateTime<Utc>> {
+    let (DER_SEQUENCE, certificate, _) = der_element(der)? else {
+        return None;
+    };
+    let (DER_SEQUENCE, tbs, _) = der_element(certificate)? else {
+        return None;
+    };
+
+    // version (optional), serialNumber, signature, issuer, then validity
+    let mut fields = tbs;
+    if der_element(fields)?.0 == DER_VERSION {
+        fields = der_element(fields)?.2;
+    }
+    for _ in 0..3 {
+        fields = der_element(fields)?.2;
+    }
+    let (DER_SEQUENCE, validity, _) = der_element(fields)? else {
+        return None;
+    };
+
+    let (_, _, not_after) = der_element(validity)?;
+    let (tag, time, _) = der_element(not_after)?;
+    parse_asn1_time(tag, std::str::from_utf8(time).ok()?)
+}
+
+/// An X.509 `UTCTime` (`YYMMDDHHMMSSZ`) or `GeneralizedTime`
+/// (`YYYYMMDDHHMMSSZ`), the only forms RFC 5280 allows.
+fn parse_asn1_time(tag: u8, time: &str) -> Option<DateTime<Utc>> {
+    let time = match tag {
+        DER_UTC_TIME => {
+            // RFC 5280: two-digit years from 50 on are 19xx
+            let year: u32 = time.get(..2)?.parse().ok()?;
+            format!("{}{}", if year >= 50 { "19" } else { "20" }, time)
+        }
+        DER_GENERALIZED_TIME => time.to_string(),
+        _ => return None,
+    };
+    NaiveDateTime::parse_from_str(&time, "%Y%m%d%H%M%SZ")
+        .ok()
+        .map(|t| t.and_utc())
+}
+
+/// Accepts any certificate. The probe only reads it; whether the server is
+/// trusted is still decided by the gRPC channel's own handshake.
+#[derive(Debug)]
+struct AcceptAnyCertificate(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// The certificate `host:port` presents, or `None` if the handshake fails.
+///
+/// Best-effort like the other extras: a failed probe never fails the check,
+/// the gRPC call reports the real connection error.
+pub async fn probe_certificate(host: &str, port: u16) -> Option<PeerCertificate> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut config = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .ok()?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(AcceptAnyCertificate(provider)))
+        .with_no_client_auth();
+    // lightwalletd only speaks gRPC, so offer what the channel would
+    config.alpn_protocols = vec![b"h2".to_vec()];
+
+    let server_name = ServerName::try_from(host.to_string()).ok()?;
+    let connector = TlsConnector::from(Arc::new(config));
+
+    let tcp = match timeout(Duration::from_secs(5), TcpStream::connect((host, port))).await {
+        Ok(Ok(tcp)) => tcp,
+        Ok(Err(e)) => {
+            info!(
+                "Certificate probe could not connect to {}:{}: {}",
+                host, port, e
+            );
+            return None;
+        }
+        Err(_) => {
+            info!(
+                "Certificate probe timed out connecting to {}:{}",
+                host, port
+            );
+            return None;
+        }
+    };
+    let tls = match timeout(Duration::from_secs(5), connector.connect(server_name, tcp)).await {
+        Ok(Ok(tls)) => tls,
+        Ok(Err(e)) => {
+            info!(
+                "Certificate probe handshake with {}:{} failed: {}",
+                host, port, e
+            );
+            return None;
+        }
+        Err(_) => {
+            info!(
+                "Certificate probe handshake with {}:{} timed out",
+                host, port
+            );
+            return None;
+        }
+    };
+
+    let (_, session) = tls.get_ref();
+    let leaf = session.peer_certificates()?.first()?;
+    Some(PeerCertificate::from_der(leaf.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_is_sha256_hex() {
+        let certificate = PeerCertificate::from_der(b"abc");
+        assert_eq!(
+            certificate.fingerprint,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(certificate.expires_at, None);
+    }
+
+    #[test]
+    fn test_cert_not_after() {
+        // Self-signed test certificates; see `openssl x509 -inform der -enddate`
+        let utc_time = include_bytes!("../testdata/utctime.der");
+        let certificate = PeerCertificate::from_der(utc_time);
+        assert_eq!(
+            certificate.expires_at.as_deref(),
+            Some("2026-03-31T12:30:00Z")
+        );
+
+        // Dates from 2050 on are encoded as GeneralizedTime
+        let generalized_time = include_bytes!("../testdata/generalizedtime.der");
+        assert_eq!(
+            cert_not_after(generalized_time).map(|t| t.to_rfc3339()),
+            Some("2051-06-01T00:00:00+00:00".to_string())
+        );
+
+        // Truncated certificates are rejected rather than misread
+        assert_eq!(cert_not_after(&utc_time[..60]), None);
+        assert_eq!(cert_not_after(&[]), None);
+    }
+
+    #[test]
+    fn test_parse_asn1_time() {
+        let parse = |tag, time| parse_asn1_time(tag, time).map(|t| t.to_rfc3339());
+        assert_eq!(
+            parse(DER_UTC_TIME, "491231235959Z").as_deref(),
+            Some("2049-12-31T23:59:59+00:00")
+        );
+        assert_eq!(
+            parse(DER_UTC_TIME, "500101000000Z").as_deref(),
+            Some("1950-01-01T00:00:00+00:00")
+        );
+        assert_eq!(parse(DER_SEQUENCE, "500101000000Z"), None);
+        assert_eq!(parse(DER_GENERALIZED_TIME, "not a time"), None);
+    }
+}
@@ -3,6 +3,27 @@
 use crate::config::ClickHouseConfig;
 use tracing::{error, info};
 
+/// Escape `value` for use inside a single-quoted ClickHouse string literal.
+/// Values are usually validated before they get here; this keeps a query
+/// safe even if a caller forgets to.
+pub fn escape_clickhouse_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\'' => escaped.push_str("\\'"),
+            // Byte escapes of the UTF-8 encoding, so C1 controls survive too
+            c if c.is_control() => {
+                for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                    escaped.push_str(&format!("\\x{:02X}", byte));
+                }
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 /// A client for interacting with ClickHouse.
 #[derive(Clone)]
 pub struct ClickHouseClient {
@@ -1,6 +1,111 @@
 //! Unified configuration management for all Hosh services.
+//!
+//! Each section reads its variables through an [`EnvReader`], which records
+//! every problem instead of stopping at the first, so [`AppConfig::load`] can
+//! report a whole misconfigured deployment at once before anything starts.
 
 use std::env;
+use std::fmt;
+use std::str::FromStr;
+
+/// Every invalid or missing environment variable found while loading.
+#[derive(Clone, Debug)]
+pub struct ConfigError {
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid configuration:")?;
+        for problem in &self.problems {
+            write!(f, "\n  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Reads environment variables, collecting problems rather than panicking.
+/// Invalid values fall back to the default so reading can carry on.
+#[derive(Debug, Default)]
+pub struct EnvReader {
+    problems: Vec<String>,
+}
+
+impl EnvReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `read` and fail with everything it reported.
+    pub fn load<T>(read: impl FnOnce(&mut EnvReader) -> T) -> Result<T, ConfigError> {
+        let mut reader = Self::new();
+        let value = read(&mut reader);
+        reader.finish(value)
+    }
+
+    /// Record a problem found by cross-checking variables.
+    pub fn problem(&mut self, problem: impl Into<String>) {
+        self.problems.push(problem.into());
+    }
+
+    /// The value of `name`, or `None` when unset or empty.
+    pub fn optional(&mut self, name: &str) -> Option<String> {
+        env::var(name).ok().filter(|v| !v.is_empty())
+    }
+
+    pub fn string(&mut self, name: &str, default: &str) -> String {
+        self.optional(name).unwrap_or_else(|| default.to_string())
+    }
+
+    pub fn required(&mut self, name: &str) -> String {
+        self.optional(name).unwrap_or_else(|| {
+            self.problem(format!("{} must be set", name));
+            String::new()
+        })
+    }
+
+    pub fn parse<T>(&mut self, name: &str, default: T) -> T
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        match self.optional(name) {
+            None => default,
+            Some(value) => value.parse().unwrap_or_else(|e| {
+                self.problem(format!("{}={:?} is invalid: {}", name, value, e));
+                default
+            }),
+        }
+    }
+
+    /// A `true`/`false` flag (case-insensitive).
+    pub fn flag(&mut self, name: &str, default: bool) -> bool {
+        match self.optional(name) {
+            None => default,
+            Some(value) if value.eq_ignore_ascii_case("true") => true,
+            Some(value) if value.eq_ignore_ascii_case("false") => false,
+            Some(value) => {
+                self.problem(format!("{}={:?} must be true or false", name, value));
+                default
+            }
+        }
+    }
+
+    pub fn finish<T>(self, value: T) -> Result<T, ConfigError> {
+        if self.problems.is_empty() {
+            Ok(value)
+        } else {
+            Err(ConfigError {
+                problems: self.problems,
+            })
+        }
+    }
+}
+
+/// Shown in place of secrets when printing the resolved configuration.
+const REDACTED: &str = "<redacted>";
 
 /// Configuration for ClickHouse database connection.
 #[derive(Clone, Debug)]
@@ -14,17 +119,18 @@ pub struct ClickHouseConfig {
 
 impl ClickHouseConfig {
     /// Create configuration from environment variables.
+    /// Panics listing every problem; use [`AppConfig::load`] to fail cleanly.
     pub fn from_env() -> Self {
+        EnvReader::load(Self::read).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn read(env: &mut EnvReader) -> Self {
         Self {
-            host: env::var("CLICKHOUSE_HOST").unwrap_or_else(|_| "chronicler".into()),
-            port: env::var("CLICKHOUSE_PORT")
-                .ok()
-                .and_then(|p| p.parse().ok())
-                .unwrap_or(8123),
-            user: env::var("CLICKHOUSE_USER").unwrap_or_else(|_| "hosh".into()),
-            password: env::var("CLICKHOUSE_PASSWORD")
-                .expect("CLICKHOUSE_PASSWORD environment variable must be set"),
-            database: env::var("CLICKHOUSE_DB").unwrap_or_else(|_| "hosh".into()),
+            host: env.string("CLICKHOUSE_HOST", "chronicler"),
+            port: env.parse("CLICKHOUSE_PORT", 8123),
+            user: env.string("CLICKHOUSE_USER", "hosh"),
+            password: env.required("CLICKHOUSE_PASSWORD"),
+            database: env.string("CLICKHOUSE_DB", "hosh"),
         }
     }
 
@@ -41,19 +147,44 @@ pub struct WorkerConfig {
     pub api_key: String,
     pub socks_proxy: Option<String>,
     pub max_concurrent_checks: usize,
+    /// Check recently failed servers first (`PRIORITIZE_FAILING`).
+    pub prioritize_failing: bool,
+    /// Also time block downloads on each check (`DEEP_CHECK`).
+    pub deep_check: bool,
+    /// Claim jobs and report results as the `<module>-canary` checker module,
+    /// for trying out a new checker version without touching production
+    /// data (`CANARY`).
+    pub canary: bool,
 }
 
 impl WorkerConfig {
     /// Create configuration from environment variables.
+    /// Panics listing every problem; use [`AppConfig::load`] to fail cleanly.
     pub fn from_env() -> Self {
+        EnvReader::load(Self::read).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn read(env: &mut EnvReader) -> Self {
+        let web_api_url = env.string("WEB_API_URL", "http://web:8080");
+        if !web_api_url.starts_with("http://") && !web_api_url.starts_with("https://") {
+            env.problem(format!(
+                "WEB_API_URL={:?} must be an http:// or https:// URL",
+                web_api_url
+            ));
+        }
+        let max_concurrent_checks = env.parse("MAX_CONCURRENT_CHECKS", 3);
+        if max_concurrent_checks == 0 {
+            env.problem("MAX_CONCURRENT_CHECKS must be at least 1");
+        }
+
         Self {
-            web_api_url: env::var("WEB_API_URL").unwrap_or_else(|_| "http://web:8080".into()),
-            api_key: env::var("API_KEY").expect("API_KEY environment variable must be set"),
-            socks_proxy: env::var("SOCKS_PROXY").ok(),
-            max_concurrent_checks: env::var("MAX_CONCURRENT_CHECKS")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(10),
+            web_api_url,
+            api_key: env.required("API_KEY"),
+            socks_proxy: env.optional("SOCKS_PROXY"),
+            max_concurrent_checks,
+            prioritize_failing: env.flag("PRIORITIZE_FAILING", false),
+            deep_check: env.flag("DEEP_CHECK", false),
+            canary: env.flag("CANARY", false),
         }
     }
 }
@@ -61,26 +192,58 @@ impl WorkerConfig {
 /// Configuration for the web service.
 #[derive(Clone, Debug)]
 pub struct WebConfig {
-    pub api_key: String,
+    /// `None` only when `REQUIRE_API_KEY` is off; the web service then falls
+    /// back to an insecure default key.
+    pub api_key: Option<String>,
+    pub require_api_key: bool,
     pub results_window_days: u32,
+    /// TTL of the results table; unset means no TTL (`DATA_RETENTION_DAYS`).
+    pub data_retention_days: Option<u64>,
+    pub cache_refresh_interval_secs: u64,
     pub bind_address: String,
     pub bind_port: u16,
 }
 
 impl WebConfig {
     /// Create configuration from environment variables.
+    /// Panics listing every problem; use [`AppConfig::load`] to fail cleanly.
     pub fn from_env() -> Self {
+        EnvReader::load(Self::read).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn read(env: &mut EnvReader) -> Self {
+        let require_api_key = env.flag("REQUIRE_API_KEY", false);
+        let api_key = if require_api_key {
+            Some(env.required("API_KEY"))
+        } else {
+            env.optional("API_KEY")
+        };
+        let cache_refresh_interval_secs = env.parse("CACHE_REFRESH_INTERVAL_SECS", 20);
+        if cache_refresh_interval_secs == 0 {
+            env.problem("CACHE_REFRESH_INTERVAL_SECS must be at least 1");
+        }
+        let data_retention_days = match env.optional("DATA_RETENTION_DAYS") {
+            None => None,
+            Some(value) => match value.parse::<u64>() {
+                Ok(days) if days > 0 => Some(days),
+                _ => {
+                    env.problem(format!(
+                        "DATA_RETENTION_DAYS={:?} must be a number of days, at least 1",
+                        value
+                    ));
+                    None
+                }
+            },
+        };
+
         Self {
-            api_key: env::var("API_KEY").expect("API_KEY environment variable must be set"),
-            results_window_days: env::var("RESULTS_WINDOW_DAYS")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(30),
-            bind_address: env::var("BIND_ADDRESS").unwrap_or_else(|_| "0.0.0.0".into()),
-            bind_port: env::var("BIND_PORT")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(8080),
+            api_key,
+            require_api_key,
+            results_window_days: env.parse("RESULTS_WINDOW_DAYS", 1),
+            data_retention_days,
+            cache_refresh_interval_secs,
+            bind_address: env.string("BIND_ADDRESS", "0.0.0.0"),
+            bind_port: env.parse("BIND_PORT", 8080),
         }
     }
 }
@@ -89,20 +252,137 @@ impl WebConfig {
 #[derive(Clone, Debug)]
 pub struct DiscoveryConfig {
     pub discovery_interval_secs: u64,
+    /// Stop checking targets with no successful check in `auto_prune_dead_days`.
+    pub auto_prune_dead: bool,
+    pub auto_prune_dead_days: u64,
+    /// Signed community list and the minisign key that verifies it.
+    pub community_list_path: Option<String>,
+    pub community_list_pubkey: Option<String>,
 }
 
 impl DiscoveryConfig {
     /// Create configuration from environment variables.
+    /// Panics listing every problem; use [`AppConfig::load`] to fail cleanly.
     pub fn from_env() -> Self {
+        EnvReader::load(Self::read).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn read(env: &mut EnvReader) -> Self {
+        let auto_prune_dead_days = env.parse("AUTO_PRUNE_DEAD_DAYS", 30);
+        if auto_prune_dead_days == 0 {
+            env.problem("AUTO_PRUNE_DEAD_DAYS must be at least 1");
+        }
+        let community_list_path = env.optional("COMMUNITY_LIST_PATH");
+        let community_list_pubkey = env.optional("COMMUNITY_LIST_PUBKEY");
+        if community_list_path.is_some() && community_list_pubkey.is_none() {
+            env.problem("COMMUNITY_LIST_PATH is set without COMMUNITY_LIST_PUBKEY");
+        }
+
         Self {
-            discovery_interval_secs: env::var("DISCOVERY_INTERVAL")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(3600),
+            discovery_interval_secs: env.parse("DISCOVERY_INTERVAL", 3600),
+            auto_prune_dead: env.flag("AUTO_PRUNE_DEAD", false),
+            auto_prune_dead_days,
+            community_list_path,
+            community_list_pubkey,
         }
     }
 }
 
+/// Which services a process runs, and so which sections it needs.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Roles {
+    pub web: bool,
+    pub checker: bool,
+    pub discovery: bool,
+}
+
+/// Configuration for the services one process runs, validated together.
+#[derive(Clone, Debug)]
+pub struct AppConfig {
+    pub clickhouse: Option<ClickHouseConfig>,
+    pub worker: Option<WorkerConfig>,
+    pub web: Option<WebConfig>,
+    pub discovery: Option<DiscoveryConfig>,
+}
+
+impl AppConfig {
+    /// Read and validate every section `roles` needs, reporting all problems.
+    pub fn load(roles: Roles) -> Result<Self, ConfigError> {
+        EnvReader::load(|env| Self {
+            clickhouse: (roles.web || roles.discovery).then(|| ClickHouseConfig::read(env)),
+            worker: roles.checker.then(|| WorkerConfig::read(env)),
+            web: roles.web.then(|| WebConfig::read(env)),
+            discovery: roles.discovery.then(|| DiscoveryConfig::read(env)),
+        })
+    }
+}
+
+/// The resolved configuration, one `NAME=value` per line, secrets redacted.
+impl fmt::Display for AppConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn line(f: &mut fmt::Formatter<'_>, name: &str, value: impl fmt::Display) -> fmt::Result {
+            writeln!(f, "{}={}", name, value)
+        }
+        fn optional(value: &Option<String>) -> &str {
+            value.as_deref().unwrap_or("")
+        }
+
+        if let Some(c) = &self.clickhouse {
+            writeln!(f, "[clickhouse]")?;
+            line(f, "CLICKHOUSE_HOST", &c.host)?;
+            line(f, "CLICKHOUSE_PORT", c.port)?;
+            line(f, "CLICKHOUSE_USER", &c.user)?;
+            line(f, "CLICKHOUSE_PASSWORD", REDACTED)?;
+            line(f, "CLICKHOUSE_DB", &c.database)?;
+        }
+        if let Some(w) = &self.web {
+            writeln!(f, "[web]")?;
+            line(
+                f,
+                "API_KEY",
+                if w.api_key.is_some() { REDACTED } else { "" },
+            )?;
+            line(f, "REQUIRE_API_KEY", w.require_api_key)?;
+            line(f, "RESULTS_WINDOW_DAYS", w.results_window_days)?;
+            line(
+                f,
+                "DATA_RETENTION_DAYS",
+                w.data_retention_days
+                    .map_or(String::new(), |d| d.to_string()),
+            )?;
+            line(
+                f,
+                "CACHE_REFRESH_INTERVAL_SECS",
+                w.cache_refresh_interval_secs,
+            )?;
+            line(f, "BIND_ADDRESS", &w.bind_address)?;
+            line(f, "BIND_PORT", w.bind_port)?;
+        }
+        if let Some(w) = &self.worker {
+            writeln!(f, "[checker]")?;
+            line(f, "WEB_API_URL", &w.web_api_url)?;
+            line(f, "API_KEY", REDACTED)?;
+            line(f, "SOCKS_PROXY", optional(&w.socks_proxy))?;
+            line(f, "MAX_CONCURRENT_CHECKS", w.max_concurrent_checks)?;
+            line(f, "PRIORITIZE_FAILING", w.prioritize_failing)?;
+            line(f, "DEEP_CHECK", w.deep_check)?;
+        }
+        if let Some(d) = &self.discovery {
+            writeln!(f, "[discovery]")?;
+            line(f, "DISCOVERY_INTERVAL", d.discovery_interval_secs)?;
+            line(f, "AUTO_PRUNE_DEAD", d.auto_prune_dead)?;
+            line(f, "AUTO_PRUNE_DEAD_DAYS", d.auto_prune_dead_days)?;
+            line(f, "COMMUNITY_LIST_PATH", optional(&d.community_list_path))?;
+            line(
+                f,
+                "COMMUNITY_LIST_PUBKEY",
+                optional(&d.community_list_pubkey),
+            )?;
+        }
+        Ok(())
+    }
+}
+
 /// Combined configuration for all services.
 #[derive(Clone, Debug)]
 pub struct Config {
@@ -124,3 +404,197 @@ impl Config {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Every variable the sections read, cleared before each test.
+    const VARS: &[&str] = &[
+        "CLICKHOUSE_HOST",
+        "CLICKHOUSE_PORT",
+        "CLICKHOUSE_USER",
+        "CLICKHOUSE_PASSWORD",
+        "CLICKHOUSE_DB",
+        "WEB_API_URL",
+        "API_KEY",
+        "SOCKS_PROXY",
+        "MAX_CONCURRENT_CHECKS",
+        "PRIORITIZE_FAILING",
+        "DEEP_CHECK",
+        "CANARY",
+        "REQUIRE_API_KEY",
+        "RESULTS_WINDOW_DAYS",
+        "DATA_RETENTION_DAYS",
+        "CACHE_REFRESH_INTERVAL_SECS",
+        "BIND_ADDRESS",
+        "BIND_PORT",
+        "DISCOVERY_INTERVAL",
+        "AUTO_PRUNE_DEAD",
+        "AUTO_PRUNE_DEAD_DAYS",
+        "COMMUNITY_LIST_PATH",
+        "COMMUNITY_LIST_PUBKEY",
+    ];
+
+    /// The environment is process-wide, so tests touching it take turns.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Load `roles` with exactly `vars` set.
+    fn load_with(vars: &[(&str, &str)], roles: Roles) -> Result<AppConfig, ConfigError> {
+        let _guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        for name in VARS {
+            env::remove_var(name);
+        }
+        for (name, value) in vars {
+            env::set_var(name, value);
+        }
+        let config = AppConfig::load(roles);
+        for (name, _) in vars {
+            env::remove_var(name);
+        }
+        config
+    }
+
+    #[test]
+    fn test_load_collects_every_problem() {
+        let err = load_with(
+            &[
+                ("CLICKHOUSE_PORT", "eighty"),
+                ("WEB_API_URL", "web:8080"),
+                ("MAX_CONCURRENT_CHECKS", "0"),
+                ("DEEP_CHECK", "yes"),
+            ],
+            Roles {
+                web: false,
+                checker: true,
+                discovery: true,
+            },
+        )
+        .unwrap_err();
+
+        assert_eq!(err.problems.len(), 6, "{:?}", err.problems);
+        let message = err.to_string();
+        assert!(message.starts_with("invalid configuration:"));
+        for name in [
+            "CLICKHOUSE_PORT",
+            "CLICKHOUSE_PASSWORD",
+            "WEB_API_URL",
+            "MAX_CONCURRENT_CHECKS",
+            "DEEP_CHECK",
+            "API_KEY",
+        ] {
+            assert!(message.contains(name), "{} missing from {}", name, message);
+        }
+    }
+
+    #[test]
+    fn test_load_validates_only_requested_roles() {
+        let discovery = Roles {
+            discovery: true,
+            ..Roles::default()
+        };
+        let checker = Roles {
+            checker: true,
+            ..Roles::default()
+        };
+
+        // The checker's API_KEY isn't needed to run discovery alone
+        let config = load_with(&[("CLICKHOUSE_PASSWORD", "secret")], discovery).unwrap();
+        assert!(config.clickhouse.is_some());
+        assert!(config.discovery.is_some());
+        assert!(config.worker.is_none());
+        assert!(config.web.is_none());
+
+        let err = load_with(&[("CLICKHOUSE_PASSWORD", "secret")], checker).unwrap_err();
+        assert_eq!(err.problems, vec!["API_KEY must be set".to_string()]);
+
+        // And a checker alone never touches ClickHouse
+        let config = load_with(&[("API_KEY", "key")], checker).unwrap();
+        assert!(config.clickhouse.is_none());
+        assert!(config.worker.is_some());
+
+        // Nothing requested, nothing to get wrong
+        assert!(load_with(&[("CLICKHOUSE_PORT", "eighty")], Roles::default()).is_ok());
+    }
+
+    #[test]
+    fn test_web_reads_bind_and_retention() {
+        let web = Roles {
+            web: true,
+            ..Roles::default()
+        };
+
+        let config = load_with(
+            &[
+                ("CLICKHOUSE_PASSWORD", "secret"),
+                ("BIND_ADDRESS", "127.0.0.1"),
+                ("BIND_PORT", "9090"),
+                ("DATA_RETENTION_DAYS", "14"),
+            ],
+            web,
+        )
+        .unwrap();
+        let config = config.web.unwrap();
+        assert_eq!(config.bind_address, "127.0.0.1");
+        assert_eq!(config.bind_port, 9090);
+        assert_eq!(config.data_retention_days, Some(14));
+
+        let config = load_with(&[("CLICKHOUSE_PASSWORD", "secret")], web)
+            .unwrap()
+            .web
+            .unwrap();
+        assert_eq!(config.bind_address, "0.0.0.0");
+        assert_eq!(config.bind_port, 8080);
+        assert_eq!(config.data_retention_days, None);
+
+        for bad in ["0", "two weeks"] {
+            let err = load_with(
+                &[
+                    ("CLICKHOUSE_PASSWORD", "secret"),
+                    ("DATA_RETENTION_DAYS", bad),
+                ],
+                web,
+            )
+            .unwrap_err();
+            assert_eq!(err.problems.len(), 1, "{:?}", err.problems);
+            assert!(err.problems[0].starts_with("DATA_RETENTION_DAYS="));
+        }
+    }
+
+    #[test]
+    fn test_display_redacts_secrets() {
+        let config = load_with(
+            &[
+                ("CLICKHOUSE_PASSWORD", "hunter2"),
+                ("API_KEY", "sekrit-key"),
+                ("CLICKHOUSE_USER", "reader"),
+            ],
+            Roles {
+                web: true,
+                checker: true,
+                discovery: true,
+            },
+        )
+        .unwrap();
+
+        let shown = config.to_string();
+        assert!(!shown.contains("hunter2"));
+        assert!(!shown.contains("sekrit-key"));
+        assert!(shown.contains(&format!("CLICKHOUSE_PASSWORD={}", REDACTED)));
+        assert_eq!(shown.matches(&format!("API_KEY={}", REDACTED)).count(), 2);
+        // Non-secrets are shown as-is
+        assert!(shown.contains("CLICKHOUSE_USER=reader"));
+
+        // An unset optional key shows empty rather than redacted
+        let config = load_with(
+            &[("CLICKHOUSE_PASSWORD", "hunter2")],
+            Roles {
+                web: true,
+                ..Roles::default()
+            },
+        )
+        .unwrap();
+        assert!(config.to_string().contains("API_KEY=\n"));
+    }
+}
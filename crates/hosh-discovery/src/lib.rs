@@ -5,14 +5,101 @@
 //! servers dynamically.
 
 use chrono::{DateTime, Utc};
+use hosh_core::clickhouse::escape_clickhouse_string;
+use hosh_core::config::DiscoveryConfig;
+use minisign_verify::{PublicKey, Signature};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::{env, error::Error, time::Duration};
+use std::{collections::HashSet, error::Error, time::Duration};
 use tokio::time;
-use tracing::{error, info};
+use tracing::{error, info, warn};
+
+/// (module, hostname, port) of a target that hasn't been online for the whole
+/// auto-prune window.
+type DeadTarget = (String, String, u16);
+
+/// Whether a target first checked at `first_checked` and last seen online at
+/// `last_online` has been failing for the whole `days` window before `now`.
+/// Targets younger than the window get the benefit of the doubt.
+fn is_dead_target(
+    first_checked: DateTime<Utc>,
+    last_online: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    days: u64,
+) -> bool {
+    let cutoff = now - chrono::Duration::days(days as i64);
+    first_checked < cutoff && last_online.is_none_or(|online| online < cutoff)
+}
+
+/// Days `find_dead_targets` looks back past the auto-prune window, so it can
+/// tell a target checked for the whole window from a new one. Pruned targets
+/// are still re-probed about once a day (see the web `get_jobs`), which keeps
+/// them dead until one of those probes succeeds.
+const DEAD_SCAN_MARGIN_DAYS: u64 = 2;
+
+/// `(module, hostname, port), ...` for a ClickHouse `IN` list, escaped.
+fn target_list<'a>(targets: impl IntoIterator<Item = &'a DeadTarget>) -> String {
+    targets
+        .into_iter()
+        .map(|(module, host, port)| {
+            format!(
+                "('{}', '{}', {})",
+                escape_clickhouse_string(module),
+                escape_clickhouse_string(host),
+                port
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// (module, hostname, port) of a target on the signed community list.
+type CommunityTarget = (String, String, u16);
 
-// Environment variable constants
-const DEFAULT_DISCOVERY_INTERVAL: u64 = 3600; // 1 hour default
+#[derive(Debug, Deserialize)]
+struct CommunityListEntry {
+    module: String,
+    hostname: String,
+    port: u16,
+}
+
+/// Load the community list at `path`, a JSON array of
+/// `{"module", "hostname", "port"}` entries, after checking it against the
+/// minisign signature in `{path}.minisig` and `pubkey` (`COMMUNITY_LIST_PUBKEY`).
+fn load_community_list(
+    path: &str,
+    pubkey: &str,
+) -> Result<HashSet<CommunityTarget>, Box<dyn Error>> {
+    let public_key =
+        PublicKey::from_base64(pubkey).map_err(|e| format!("Invalid public key: {}", e))?;
+    let signature_path = format!("{}.minisig", path);
+    let signature = Signature::from_file(&signature_path)
+        .map_err(|e| format!("Unreadable signature {}: {}", signature_path, e))?;
+    let contents = std::fs::read(path)?;
+    public_key
+        .verify(&contents, &signature, false)
+        .map_err(|e| format!("Signature verification failed: {}", e))?;
+
+    let entries: Vec<CommunityListEntry> = serde_json::from_slice(&contents)?;
+    let mut list = HashSet::new();
+    for entry in entries {
+        // Signed doesn't mean well-formed, and these end up in SQL
+        let valid_host = !entry.hostname.is_empty()
+            && entry
+                .hostname
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_');
+        if !matches!(entry.module.as_str(), "zec" | "btc") || !valid_host {
+            return Err(format!(
+                "Invalid entry {} {}:{}",
+                entry.module, entry.hostname, entry.port
+            )
+            .into());
+        }
+        list.insert((entry.module, entry.hostname, entry.port));
+    }
+    Ok(list)
+}
 
 // ClickHouse configuration
 struct ClickHouseConfig {
@@ -24,18 +111,15 @@ struct ClickHouseConfig {
 }
 
 impl ClickHouseConfig {
-    fn from_env() -> Self {
-        let host = env::var("CLICKHOUSE_HOST").unwrap_or_else(|_| "chronicler".into());
-        let port = env::var("CLICKHOUSE_PORT").unwrap_or_else(|_| "8123".into());
-        let url = format!("http://{}:{}", host, port);
+    fn new(config: hosh_core::config::ClickHouseConfig) -> Self {
+        let url = config.url();
         info!("Configuring ClickHouse connection to {}", url);
 
         Self {
             url,
-            user: env::var("CLICKHOUSE_USER").unwrap_or_else(|_| "hosh".into()),
-            password: env::var("CLICKHOUSE_PASSWORD")
-                .expect("CLICKHOUSE_PASSWORD environment variable must be set"),
-            database: env::var("CLICKHOUSE_DB").unwrap_or_else(|_| "hosh".into()),
+            user: config.user,
+            password: config.password,
+            database: config.database,
             client: reqwest::Client::new(),
         }
     }
@@ -107,6 +191,150 @@ impl ClickHouseConfig {
         Ok(())
     }
 
+    /// Find targets that have been checked for longer than `days` without a single
+    /// successful check in that window (see `is_dead_target`).
+    ///
+    /// Only the last `days + DEAD_SCAN_MARGIN_DAYS` of `results` are scanned.
+    /// A pruned target stays dead through its daily re-probes and comes back
+    /// as soon as one of them succeeds.
+    async fn find_dead_targets(&self, days: u64) -> Result<HashSet<DeadTarget>, Box<dyn Error>> {
+        // maxIf over no online rows is the epoch, i.e. never online
+        let query = format!(
+            "SELECT checker_module, hostname, port,
+                toUnixTimestamp(min(checked_at)),
+                toUnixTimestamp(maxIf(checked_at, status = 'online'))
+             FROM {db}.results
+             WHERE checked_at >= now() - INTERVAL {scan_days} DAY
+             GROUP BY checker_module, hostname, port
+             FORMAT TabSeparated",
+            db = self.database,
+            scan_days = days + DEAD_SCAN_MARGIN_DAYS,
+        );
+        let result = self.execute_query(&query).await?;
+
+        let now = Utc::now();
+        let at = |secs: &str| {
+            secs.parse::<i64>()
+                .ok()
+                .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        };
+        let mut dead = HashSet::new();
+        for line in result.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [module, hostname, port, first_checked, last_online] = fields[..] else {
+                warn!("Skipping malformed dead target row: {}", line);
+                continue;
+            };
+            let (Ok(port), Some(first_checked)) = (port.parse::<u16>(), at(first_checked)) else {
+                warn!("Skipping dead target row with bad port or time: {}", line);
+                continue;
+            };
+            let last_online = at(last_online).filter(|t| t.timestamp() > 0);
+            if is_dead_target(first_checked, last_online, now, days) {
+                dead.insert((module.to_string(), hostname.to_string(), port));
+            }
+        }
+        Ok(dead)
+    }
+
+    /// Mark dead targets found by `find_dead_targets` as `pruned`, which stops
+    /// them being handed out to checkers. The row is kept, so discovery doesn't
+    /// re-add it and `restore_pruned_targets` can bring it back. User-submitted
+    /// and community targets are never pruned.
+    async fn prune_dead_targets(&self, dead: &HashSet<DeadTarget>) -> Result<(), Box<dyn Error>> {
+        if dead.is_empty() {
+            return Ok(());
+        }
+
+        let query = format!(
+            "SELECT module, hostname, port FROM {}.targets WHERE user_submitted = false AND community = false AND pruned = false AND (module, hostname, port) IN ({}) FORMAT TabSeparated",
+            self.database,
+            target_list(dead)
+        );
+        let result = self.execute_query(&query).await?;
+
+        for line in result.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [module, hostname, port] = fields[..] else {
+                warn!("Skipping malformed target row: {}", line);
+                continue;
+            };
+            let query = format!(
+                "ALTER TABLE {}.targets UPDATE pruned = true WHERE module = '{}' AND hostname = '{}' AND port = {} AND user_submitted = false AND community = false",
+                self.database,
+                escape_clickhouse_string(module),
+                escape_clickhouse_string(hostname),
+                port
+            );
+            self.execute_query(&query).await?;
+            info!(
+                "Auto-pruned dead target: {} {}:{} (no successful checks in window)",
+                module, hostname, port
+            );
+        }
+        Ok(())
+    }
+
+    /// Clear `pruned` on targets that no longer count as dead (a re-probe
+    /// succeeded) or that have since become user-submitted or community
+    /// targets, so checkers pick them up again.
+    async fn restore_pruned_targets(
+        &self,
+        dead: &HashSet<DeadTarget>,
+    ) -> Result<(), Box<dyn Error>> {
+        let still_dead = if dead.is_empty() {
+            "false".to_string()
+        } else {
+            format!("(module, hostname, port) IN ({})", target_list(dead))
+        };
+        let query = format!(
+            "SELECT module, hostname, port FROM {db}.targets WHERE pruned = true AND (user_submitted = true OR community = true OR NOT {still_dead}) FORMAT TabSeparated",
+            db = self.database,
+            still_dead = still_dead
+        );
+        let result = self.execute_query(&query).await?;
+
+        for line in result.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [module, hostname, port] = fields[..] else {
+                warn!("Skipping malformed target row: {}", line);
+                continue;
+            };
+            let query = format!(
+                "ALTER TABLE {}.targets UPDATE pruned = false WHERE module = '{}' AND hostname = '{}' AND port = {}",
+                self.database,
+                escape_clickhouse_string(module),
+                escape_clickhouse_string(hostname),
+                port
+            );
+            self.execute_query(&query).await?;
+            info!(
+                "Restored pruned target: {} {}:{} (checks resume)",
+                module, hostname, port
+            );
+        }
+        Ok(())
+    }
+
+    /// Make the `community` flag of every target match `list`.
+    async fn apply_community_list(
+        &self,
+        list: &HashSet<CommunityTarget>,
+    ) -> Result<(), Box<dyn Error>> {
+        let community = if list.is_empty() {
+            "false".to_string()
+        } else {
+            format!("(module, hostname, port) IN ({})", target_list(list))
+        };
+        let query = format!(
+            "ALTER TABLE {}.targets UPDATE community = {} WHERE 1",
+            self.database, community
+        );
+        self.execute_query(&query).await?;
+        info!("Applied community list with {} targets", list.len());
+        Ok(())
+    }
+
     async fn insert_target(
         &self,
         module: &str,
@@ -416,17 +644,25 @@ async fn get_server_details(
 async fn update_servers(
     client: &reqwest::Client,
     clickhouse: &ClickHouseConfig,
+    community_list: Option<&HashSet<CommunityTarget>>,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    // A verified community list overrides the static flags
+    let is_community = |module: &str, host: &str, port: u16, default: bool| match community_list {
+        Some(list) => list.contains(&(module.into(), host.into(), port)),
+        None => default,
+    };
+
     // Process ZEC servers first
     info!("Processing {} ZEC servers...", ZEC_SERVERS.len());
     for (host, port, community) in ZEC_SERVERS {
+        let community = is_community("zec", host, *port, *community);
         info!(
             "Processing ZEC server: {}:{} (community: {})",
             host, port, community
         );
         if !clickhouse.target_exists("zec", host, *port).await? {
             if let Err(e) = clickhouse
-                .insert_target("zec", host, *port, *community)
+                .insert_target("zec", host, *port, community)
                 .await
             {
                 error!("Failed to insert ZEC server {}:{}: {}", host, port, e);
@@ -450,13 +686,16 @@ async fn update_servers(
             .and_then(|s| s.parse::<u16>().ok())
             .unwrap_or(50001);
         info!("Processing BTC server: {}:{}", host, port);
-
+        let community = is_community("btc", &host, port, false);
         if !clickhouse.target_exists("btc", &host, port).await? {
             // Try to get details but don't require success
             let details = get_server_details(client, &host, port).await;
             match details {
                 Ok(_) => {
-                    if let Err(e) = clickhouse.insert_target("btc", &host, port, false).await {
+                    if let Err(e) = clickhouse
+                        .insert_target("btc", &host, port, community)
+                        .await
+                    {
                         error!("Failed to insert BTC server {}:{}: {}", host, port, e);
                     }
                 }
@@ -466,7 +705,10 @@ async fn update_servers(
                         "Could not verify BTC server {}:{}: {}, but inserting anyway",
                         host, port, e
                     );
-                    if let Err(e) = clickhouse.insert_target("btc", &host, port, false).await {
+                    if let Err(e) = clickhouse
+                        .insert_target("btc", &host, port, community)
+                        .await
+                    {
                         error!("Failed to insert BTC server {}:{}: {}", host, port, e);
                     }
                 }
@@ -479,30 +721,83 @@ async fn update_servers(
     Ok(())
 }
 
-/// Run the discovery service.
+/// Run the discovery service, reading its configuration from the environment.
 ///
 /// This is the main entry point for the discovery service.
 /// It periodically discovers and registers new servers in the database.
 pub async fn run() -> Result<(), Box<dyn Error + Send + Sync>> {
+    run_with_config(
+        hosh_core::config::ClickHouseConfig::from_env(),
+        DiscoveryConfig::from_env(),
+    )
+    .await
+}
+
+/// Run the discovery service with configuration already loaded by
+/// [`hosh_core::config::AppConfig::load`].
+pub async fn run_with_config(
+    clickhouse: hosh_core::config::ClickHouseConfig,
+    config: DiscoveryConfig,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
     info!("Starting discovery service...");
 
     // Initialize ClickHouse client
-    let clickhouse = ClickHouseConfig::from_env();
+    let clickhouse = ClickHouseConfig::new(clickhouse);
     let http_client = Client::new();
     info!("Initialized ClickHouse client");
 
-    // Get discovery interval from environment or use default
-    let discovery_interval = env::var("DISCOVERY_INTERVAL")
-        .ok()
-        .and_then(|s| s.parse::<u64>().ok())
-        .unwrap_or(DEFAULT_DISCOVERY_INTERVAL);
+    let DiscoveryConfig {
+        discovery_interval_secs: discovery_interval,
+        // Optionally stop checking targets that never come back online (AUTO_PRUNE_DEAD=true)
+        auto_prune_dead,
+        auto_prune_dead_days,
+        // Signed community list (COMMUNITY_LIST_PATH, verified with COMMUNITY_LIST_PUBKEY)
+        community_list_path,
+        community_list_pubkey,
+    } = config;
 
     info!("Discovery interval set to {} seconds", discovery_interval);
+    if auto_prune_dead {
+        info!(
+            "Auto-pruning targets with no successful checks in {} days",
+            auto_prune_dead_days
+        );
+    }
+
+    let mut community_list: Option<HashSet<CommunityTarget>> = None;
 
     loop {
         info!("Starting discovery cycle...");
 
-        match update_servers(&http_client, &clickhouse).await {
+        if let (Some(path), Some(pubkey)) = (&community_list_path, &community_list_pubkey) {
+            match load_community_list(path, pubkey) {
+                Ok(list) if community_list.as_ref() == Some(&list) => {}
+                Ok(list) => match clickhouse.apply_community_list(&list).await {
+                    Ok(()) => community_list = Some(list),
+                    Err(e) => error!("Failed to apply community list: {}", e),
+                },
+                Err(e) => error!(
+                    "Rejected community list {}: {}; keeping the previous one",
+                    path, e
+                ),
+            }
+        }
+
+        if auto_prune_dead {
+            match clickhouse.find_dead_targets(auto_prune_dead_days).await {
+                Ok(dead) => {
+                    if let Err(e) = clickhouse.prune_dead_targets(&dead).await {
+                        error!("Failed to prune dead targets: {}", e);
+                    }
+                    if let Err(e) = clickhouse.restore_pruned_targets(&dead).await {
+                        error!("Failed to restore pruned targets: {}", e);
+                    }
+                }
+                Err(e) => error!("Failed to find dead targets: {}", e),
+            }
+        }
+
+        match update_servers(&http_client, &clickhouse, community_list.as_ref()).await {
             Ok(_) => info!("Discovery cycle completed successfully"),
             Err(e) => error!("Error during discovery cycle: {}", e),
         }
@@ -514,3 +809,104 @@ pub async fn run() -> Result<(), Box<dyn Error + Send + Sync>> {
         time::sleep(Duration::from_secs(discovery_interval)).await;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Public key matching `testdata/community.json.minisig`.
+    const TEST_PUBKEY: &str = "RWQBI0VniavN7wOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4";
+
+    fn testdata(name: &str) -> String {
+        format!("{}/testdata/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    /// A scratch copy of the signed list, so tests can tamper with it.
+    fn scratch_list(test: &str, contents: &[u8], signature: Option<&[u8]>) -> String {
+        let dir =
+            std::env::temp_dir().join(format!("hosh-discovery-{}-{}", test, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("community.json");
+        std::fs::write(&path, contents).unwrap();
+        let signature_path = dir.join("community.json.minisig");
+        match signature {
+            Some(signature) => std::fs::write(&signature_path, signature).unwrap(),
+            None => {
+                let _ = std::fs::remove_file(&signature_path);
+            }
+        }
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_load_community_list_valid_signature() {
+        let list = load_community_list(&testdata("community.json"), TEST_PUBKEY).unwrap();
+        let expected: HashSet<CommunityTarget> = [
+            ("zec".to_string(), "zec.community.example".to_string(), 443),
+            (
+                "btc".to_string(),
+                "btc.community.example".to_string(),
+                50002,
+            ),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(list, expected);
+    }
+
+    #[test]
+    fn test_load_community_list_tampered() {
+        let contents = std::fs::read(testdata("community.json")).unwrap();
+        let signature = std::fs::read(testdata("community.json.minisig")).unwrap();
+        let tampered = String::from_utf8(contents)
+            .unwrap()
+            .replace("zec.community.example", "zec.attacker.example");
+        let path = scratch_list("tampered", tampered.as_bytes(), Some(&signature));
+
+        let err = load_community_list(&path, TEST_PUBKEY).unwrap_err();
+        assert!(
+            err.to_string().contains("Signature verification failed"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_load_community_list_missing_signature() {
+        let contents = std::fs::read(testdata("community.json")).unwrap();
+        let path = scratch_list("unsigned", &contents, None);
+
+        let err = load_community_list(&path, TEST_PUBKEY).unwrap_err();
+        assert!(err.to_string().contains("Unreadable signature"), "{}", err);
+    }
+
+    #[test]
+    fn test_is_dead_target() {
+        let now = DateTime::parse_from_rfc3339("2025-06-30T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let days_ago = |days| now - chrono::Duration::days(days);
+
+        // Failing for the whole window, whether or not it was ever online
+        assert!(is_dead_target(days_ago(60), None, now, 30));
+        assert!(is_dead_target(days_ago(60), Some(days_ago(31)), now, 30));
+
+        // One success inside the window keeps it
+        assert!(!is_dead_target(days_ago(60), Some(days_ago(29)), now, 30));
+
+        // Too new to judge, even if it has never been online
+        assert!(!is_dead_target(days_ago(29), None, now, 30));
+    }
+
+    #[test]
+    fn test_target_list_escapes() {
+        let targets = [
+            ("btc".to_string(), "a.example".to_string(), 50002),
+            ("zec".to_string(), "b'); DROP".to_string(), 443),
+        ];
+        assert_eq!(
+            target_list(&targets),
+            "('btc', 'a.example', 50002), ('zec', 'b\\'); DROP', 443)"
+        );
+    }
+}
@@ -3,6 +3,7 @@
 //! This is the main binary that can run different roles of the Hosh system.
 
 use clap::Parser;
+use hosh_core::config::{AppConfig, Roles};
 use std::collections::HashSet;
 use std::env;
 use tracing::{error, info};
@@ -23,6 +24,11 @@ struct Cli {
     /// Can also be set via CHECKER_LOCATION env var (CLI flag takes precedence)
     #[arg(long)]
     location: Option<String>,
+
+    /// Print the resolved configuration for the selected roles (secrets
+    /// redacted) and exit
+    #[arg(long)]
+    print_config: bool,
 }
 
 const VALID_ROLES: &[&str] = &["web", "checker-btc", "checker-zec", "discovery", "all"];
@@ -80,6 +86,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         std::process::exit(1);
     }
 
+    // Validate every role's environment up front, so a misconfiguration
+    // fails here with the full list instead of panicking inside a role
+    let config_roles = Roles {
+        web: roles.contains("web"),
+        checker: roles.contains("checker-btc") || roles.contains("checker-zec"),
+        discovery: roles.contains("discovery"),
+    };
+    let config = match AppConfig::load(config_roles) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if cli.print_config {
+        print!("{}", config);
+        return Ok(());
+    }
+
     info!("Starting Hosh with roles: {:?}", roles);
 
     // Resolve location: CLI flag > env var > default
@@ -107,30 +132,65 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         info!("Starting discovery service...");
     }
 
+    // Each section was loaded above exactly when a role needing it runs, and
+    // a disabled branch's future is never polled
+    let AppConfig {
+        clickhouse,
+        worker,
+        web,
+        discovery,
+    } = config;
+    let web_clickhouse = clickhouse.clone();
+    let btc_worker = worker.clone();
+
     // Use tokio::select! to run all enabled roles concurrently
     // Each branch will only be active if the role is enabled
     let btc_location = location.clone();
     let zec_location = location.clone();
     tokio::select! {
-        result = async { hosh_web::run().await }, if run_web => {
+        result = async {
+            hosh_web::run_with_config(
+                web.expect("web config is loaded for the web role"),
+                web_clickhouse.expect("ClickHouse config is loaded for the web role"),
+            )
+            .await
+        }, if run_web => {
             match result {
                 Ok(()) => info!("Web server completed"),
                 Err(e) => error!("Web server error: {}", e),
             }
         }
-        result = async { hosh_checker_btc::run_with_location(&btc_location).await }, if run_btc => {
+        result = async {
+            hosh_checker_btc::run_with_config(
+                btc_worker.expect("checker config is loaded for checker roles"),
+                &btc_location,
+            )
+            .await
+        }, if run_btc => {
             match result {
                 Ok(()) => info!("BTC checker completed"),
                 Err(e) => error!("BTC checker error: {}", e),
             }
         }
-        result = async { hosh_checker_zec::run_with_location(&zec_location).await }, if run_zec => {
+        result = async {
+            hosh_checker_zec::run_with_config(
+                worker.expect("checker config is loaded for checker roles"),
+                &zec_location,
+            )
+            .await
+        }, if run_zec => {
             match result {
                 Ok(()) => info!("ZEC checker completed"),
                 Err(e) => error!("ZEC checker error: {}", e),
             }
         }
-        result = async { hosh_discovery::run().await }, if run_discovery => {
+        result = async {
+            hosh_discovery::run_with_config(
+                clickhouse.expect("ClickHouse config is loaded for the discovery role"),
+                discovery.expect("discovery config is loaded for the discovery role"),
+            )
+            .await
+        }, if run_discovery => {
             match result {
                 Ok(()) => info!("Discovery service completed"),
                 Err(e) => error!("Discovery service error: {}", e),